@@ -121,6 +121,186 @@ pub struct Config {
     pub future_poll_size: usize,
     pub hibernate_regions: bool,
 
+    // Number of IO writer threads a `WriteRouter` may spread peer writes
+    // across.
+    pub store_io_pool_size: usize,
+    // Maximum number of peers that may be mid-reschedule between writers at
+    // the same time. 0 disables rescheduling entirely.
+    pub io_reschedule_concurrent_max_count: usize,
+    // A peer only becomes eligible to reschedule to a different writer once
+    // it has been sending to its current writer for at least this long.
+    pub io_reschedule_hotpot_duration: ReadableDuration,
+    // Once a reschedule completes, a peer stays pinned to its new writer for
+    // at least this long before `io_reschedule_hotpot_duration` alone would
+    // otherwise make it eligible to move again, so a writer that just picked
+    // up a peer isn't immediately abandoned on the next transient load blip.
+    // 0 disables the extra cooldown, leaving `io_reschedule_hotpot_duration`
+    // as the only gate.
+    pub io_reschedule_cooldown: ReadableDuration,
+    // If non-zero, limits a peer's `WriteRouter` reschedule targets to a
+    // fixed-size subset of writers, chosen the first time it reschedules, so
+    // its working set stays on a small number of writer threads for
+    // locality.
+    pub io_writer_affinity_set_size: usize,
+    // A reschedule candidate whose reported queue depth is at or above this
+    // value is rejected so we don't relocate congestion onto an already
+    // busy writer. `usize::MAX` disables the check.
+    pub io_reschedule_admission_max_load: usize,
+    // Maximum number of pending write msgs flushed out of a `WriteRouter`'s
+    // buffer per `check_new_persisted` call once a reschedule has completed.
+    // 0 means flush everything in one go.
+    pub io_reschedule_flush_budget: usize,
+    // When true, reschedule target selection uses smooth weighted
+    // round-robin (see `WriteSenders::set_writer_weight`) instead of
+    // weighted-random, guaranteeing exact per-writer selection shares over
+    // a full cycle rather than matching only in expectation.
+    pub io_reschedule_use_weighted_round_robin: bool,
+    // When true, reschedule candidate selection always excludes the peer's
+    // current writer, guaranteeing a reschedule actually moves traffic
+    // rather than occasionally self-picking.
+    pub io_reschedule_always_move: bool,
+    // Reserves specific `WriteMsg` variants (by name, e.g. "WriteTask") to a
+    // fixed writer index, letting operators declaratively isolate a variant
+    // onto a dedicated writer. Indices are validated against
+    // `store_io_pool_size` in `validate()`.
+    pub io_writer_variant_overrides: std::collections::HashMap<String, usize>,
+    // When true, samples `WriteRouter::should_send`'s own execution time
+    // into a histogram on every call, to detect whether a new selection
+    // strategy regresses this hot-path's CPU cost. Off by default since
+    // it adds a clock read to every write on the fast path.
+    pub io_reschedule_measure_selection_latency: bool,
+    // Bounds how long a direct send to a writer may block on a full queue
+    // before giving up and buffering the msg for a later retry instead. 0
+    // blocks indefinitely, matching the historical behavior.
+    pub io_blocking_send_timeout: ReadableDuration,
+    // When true, reschedule candidate selection always picks the writer
+    // with the lowest combined score of reported load (see
+    // `WriteSenders::set_writer_load`) and backlog age (see
+    // `WriteSenders::set_writer_backlog_age`), rather than weighted-random
+    // or round-robin.
+    pub io_reschedule_prefer_least_loaded: bool,
+    // When a reschedule's target writer is marked avoided (e.g. via
+    // `WriteSenders::evacuate`) by the time the reschedule completes, by
+    // default the peer redirects to another writer instead of completing
+    // onto a writer that's draining. Set true to restore the historical
+    // behavior of completing onto it regardless.
+    pub io_reschedule_complete_onto_draining_target: bool,
+    // When true, a msg buffered via the full-channel blocking-send fallback
+    // (see `Config::io_blocking_send_timeout`) is not added to
+    // `STORE_IO_RESCHEDULE_PENDING_TASKS_TOTAL_GAUGE`, for callers that
+    // already account for the msg's resource usage themselves before
+    // attempting the send and would otherwise see it counted twice.
+    pub io_skip_pending_task_accounting_on_blocking_fallback: bool,
+    // When non-zero, logs every Nth `WriteRouter::send_write_msg` routing
+    // decision at debug level, counted per-peer. 0 disables the log
+    // entirely, since logging every single write would be far too noisy
+    // for production use.
+    pub io_route_log_sample: u64,
+    // When non-zero, governs how often a store-level timer should emit an
+    // info log summarizing reschedule activity across all of its peers' write
+    // routers (see `write_router::summarize_reschedule_activity`), for
+    // at-a-glance operational awareness without a metrics backend. 0
+    // disables the summary log entirely.
+    pub io_reschedule_summary_log_interval: ReadableDuration,
+    // Caps how many reschedules between the same ordered pair of writers
+    // (e.g. A -> B) may happen within `io_reschedule_pair_rate_limit_window`,
+    // to break a pathological bounce loop where peers churn back and forth
+    // between two writers. 0 disables the cap.
+    pub io_reschedule_pair_rate_limit_max: usize,
+    // The window `io_reschedule_pair_rate_limit_max` is counted over. Reset
+    // once it elapses rather than sliding.
+    pub io_reschedule_pair_rate_limit_window: ReadableDuration,
+    // When true, reschedule candidate selection always picks the writer
+    // with the fewest peers currently counted against it (see
+    // `WriteSenders::active_peer_count`), so simultaneous reschedules spread
+    // evenly across the pool instead of coincidentally piling onto the same
+    // writer. Takes priority over `io_reschedule_prefer_least_loaded`.
+    pub io_reschedule_spread_target: bool,
+    // Caps how many msgs a single `WriteRouter` will buffer while
+    // `WriteSenders::quiesce` is in effect, so a long quiesce window can't
+    // grow an unbounded backlog. 0 disables the cap.
+    pub io_quiesce_max_buffered: usize,
+    // When true, reschedule candidate selection picks the writer with the
+    // fewest msgs actually sitting in its channel right now, rather than an
+    // externally-reported load figure (see `io_reschedule_prefer_least_loaded`).
+    // Ties fall back to random choice to avoid herding.
+    pub io_reschedule_prefer_shortest_queue: bool,
+    // Caps how many msgs a single `WriteRouter` will buffer while a
+    // reschedule is in flight. If `check_new_persisted` never sees a high
+    // enough persisted number and the buffer exceeds this, the reschedule
+    // is abandoned and everything buffered drains to the original writer.
+    // 0 disables the cap.
+    pub io_reschedule_pending_max_count: usize,
+    // How long a `WriteRouter` waits before retrying a reschedule attempt
+    // that failed to acquire a slot, land on an admissible candidate, or
+    // clear the pair rate limit. Must be non-zero, or a perpetually busy
+    // pool would spin retrying every single send.
+    pub io_reschedule_retry_interval: ReadableDuration,
+    // Caps how far `io_reschedule_retry_interval` may back off after
+    // repeated consecutive retry failures: the delay doubles on each
+    // failure up to `io_reschedule_retry_interval *
+    // io_reschedule_retry_backoff_max_multiplier`, then holds there. Reset
+    // to the base interval as soon as a reschedule successfully starts.
+    pub io_reschedule_retry_backoff_max_multiplier: usize,
+    // When true, reschedule candidate selection draws independently at
+    // random with odds proportional to each writer's weight (see
+    // `WriteSenders::set_writer_weight`/`set_weights`), matching the
+    // configured weights only in expectation over a large sample. Unlike
+    // `io_reschedule_use_weighted_round_robin`, it gives no guarantee over
+    // any particular window; `io_reschedule_use_weighted_round_robin` takes
+    // priority when both are set.
+    pub io_reschedule_weighted_random_selection: bool,
+    // When true, reschedule candidate selection biases toward whichever
+    // writer has historically drained fastest, via an EWMA of dispatch
+    // latency (see `WriteSenders::record_probe_latency`) fed into the same
+    // independent weighted-random draw as `io_reschedule_weighted_random_selection`,
+    // instead of a manually configured weight. Falls back to uniform random
+    // once no writer has any latency history yet. Takes priority over
+    // `io_reschedule_weighted_random_selection` when both are set.
+    pub io_reschedule_sticky: bool,
+    // How many reschedule slots past `io_reschedule_concurrent_max_count` a
+    // `WriteRouter` with `set_reschedule_priority` set above zero may still
+    // acquire, so a high-priority peer isn't starved behind a pool of
+    // ordinary peers that filled every slot first. `0` disables preemption.
+    pub io_reschedule_priority_overflow_budget: usize,
+    // Fraction, in [0, 1], of `io_reschedule_hotpot_duration` to randomly
+    // jitter by (plus or minus) each time it's used to set a `WriteRouter`'s
+    // `next_retry_time`, so a batch of peers that all just rescheduled
+    // together don't all become eligible to reschedule again at the exact
+    // same instant. `0` (the default) disables jitter.
+    pub io_reschedule_hotpot_jitter: f64,
+    // When true, a `WriteRouter` built via `new_with_region_id` picks
+    // `region_id % store_io_pool_size` as its initial writer instead of the
+    // default `0`, so replicas of the same region consistently start out on
+    // the same writer across stores.
+    pub store_io_hash_by_region: bool,
+    // When true, a `send` that finds its target writer's channel full tries
+    // one other, less-loaded writer via a non-blocking send before falling
+    // back to the existing blocking/buffering path. Only applies while there
+    // is no `last_unpersisted` outstanding, so per-peer ordering is never put
+    // at risk; the diverted message does not change the peer's `writer_id`.
+    pub store_io_spill_on_full: bool,
+    // Fraction, in [0, 1], of a writer channel's capacity above which
+    // `WriteRouter::backpressured` reports the peer should throttle how fast
+    // it keeps generating readies. Only meaningful for writers backed by a
+    // bounded channel; unbounded channels have no capacity to measure against
+    // and are never reported as backpressured.
+    pub store_io_backpressure_ratio: f64,
+    // When non-zero, a `WriteRouter` whose `pending_len` exceeds this while
+    // buffering for a reschedule logs a rate-limited warning, so a single
+    // pathological peer stands out instead of being hidden inside the
+    // store-wide `STORE_IO_RESCHEDULE_PENDING_TASKS_TOTAL_GAUGE` aggregate.
+    // `0` disables the check.
+    pub io_reschedule_pending_warn_threshold: usize,
+    // Caps how many reschedules may *start* per second across every peer
+    // sharing a `WriteSenders`, via a token bucket that also allows a burst
+    // of up to one second's worth of accumulated tokens. Guards against a
+    // wave of reschedules completing at once and immediately triggering
+    // another wave, which `io_reschedule_concurrent_max_count` alone can't
+    // prevent since it only bounds how many are in flight, not how fast new
+    // ones start. `0` disables the limit.
+    pub io_reschedule_max_rate: u64,
+
     // Deprecated! These two configuration has been moved to Coprocessor.
     // They are preserved for compatibility check.
     #[doc(hidden)]
@@ -193,6 +373,40 @@ impl Default for Config {
             store_pool_size: 2,
             future_poll_size: 1,
             hibernate_regions: true,
+            store_io_pool_size: 1,
+            io_reschedule_concurrent_max_count: 4,
+            io_reschedule_hotpot_duration: ReadableDuration::secs(2),
+            io_reschedule_cooldown: ReadableDuration::secs(0),
+            io_writer_affinity_set_size: 0,
+            io_reschedule_admission_max_load: usize::max_value(),
+            io_reschedule_flush_budget: 0,
+            io_reschedule_use_weighted_round_robin: false,
+            io_reschedule_always_move: false,
+            io_writer_variant_overrides: std::collections::HashMap::new(),
+            io_reschedule_measure_selection_latency: false,
+            io_blocking_send_timeout: ReadableDuration::millis(0),
+            io_reschedule_prefer_least_loaded: false,
+            io_reschedule_complete_onto_draining_target: false,
+            io_skip_pending_task_accounting_on_blocking_fallback: false,
+            io_route_log_sample: 0,
+            io_reschedule_summary_log_interval: ReadableDuration::secs(0),
+            io_reschedule_pair_rate_limit_max: 0,
+            io_reschedule_pair_rate_limit_window: ReadableDuration::secs(60),
+            io_reschedule_spread_target: false,
+            io_quiesce_max_buffered: 0,
+            io_reschedule_prefer_shortest_queue: false,
+            io_reschedule_pending_max_count: 1024,
+            io_reschedule_retry_interval: ReadableDuration::millis(10),
+            io_reschedule_retry_backoff_max_multiplier: 10,
+            io_reschedule_weighted_random_selection: false,
+            io_reschedule_sticky: false,
+            io_reschedule_priority_overflow_budget: 0,
+            io_reschedule_hotpot_jitter: 0.0,
+            store_io_hash_by_region: false,
+            store_io_spill_on_full: false,
+            store_io_backpressure_ratio: 0.8,
+            io_reschedule_pending_warn_threshold: 0,
+            io_reschedule_max_rate: 0,
 
             // They are preserved for compatibility check.
             region_max_size: ReadableSize(0),
@@ -346,6 +560,46 @@ impl Config {
         if self.future_poll_size == 0 {
             return Err(box_err!("future-poll-size should be greater than 0."));
         }
+        for (variant, writer_id) in &self.io_writer_variant_overrides {
+            if *writer_id >= self.store_io_pool_size {
+                return Err(box_err!(
+                    "io-writer-variant-overrides.{} writer index {} is out of range for store-io-pool-size {}",
+                    variant,
+                    writer_id,
+                    self.store_io_pool_size
+                ));
+            }
+        }
+        if self.io_reschedule_retry_interval.as_millis() == 0 {
+            return Err(box_err!(
+                "io-reschedule-retry-interval should be greater than 0."
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.io_reschedule_hotpot_jitter) {
+            return Err(box_err!(
+                "io-reschedule-hotpot-jitter should be in [0, 1]."
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.store_io_backpressure_ratio) {
+            return Err(box_err!(
+                "store-io-backpressure-ratio should be in [0, 1]."
+            ));
+        }
+        if self.io_reschedule_concurrent_max_count > 0
+            && self.io_reschedule_hotpot_duration.as_millis() == 0
+        {
+            return Err(box_err!(
+                "io-reschedule-hotpot-duration should be greater than 0 when \
+                 io-reschedule-concurrent-max-count is non-zero, or a peer \
+                 becomes eligible to reschedule again immediately."
+            ));
+        }
+        if self.io_reschedule_retry_interval.as_millis() > self.io_reschedule_hotpot_duration.as_millis() {
+            warn!(
+                "io-reschedule-retry-interval is longer than io-reschedule-hotpot-duration, \
+                 a stalled retry may outlive the hotpot window it was waiting on"
+            );
+        }
         Ok(())
     }
 
@@ -617,5 +871,21 @@ mod tests {
         cfg = Config::new();
         cfg.future_poll_size = 0;
         assert!(cfg.validate().is_err());
+
+        cfg = Config::new();
+        cfg.io_reschedule_concurrent_max_count = 4;
+        cfg.io_reschedule_hotpot_duration = ReadableDuration::secs(0);
+        assert!(cfg.validate().is_err());
+
+        cfg = Config::new();
+        cfg.io_reschedule_concurrent_max_count = 0;
+        cfg.io_reschedule_hotpot_duration = ReadableDuration::secs(0);
+        cfg.validate().unwrap();
+
+        cfg = Config::new();
+        cfg.io_reschedule_hotpot_duration = ReadableDuration::secs(1);
+        cfg.io_reschedule_retry_interval = ReadableDuration::secs(2);
+        // Only a warning, not a rejection.
+        cfg.validate().unwrap();
     }
 }
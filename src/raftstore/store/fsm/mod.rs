@@ -10,6 +10,7 @@ mod metrics;
 mod peer;
 mod router;
 pub mod store;
+mod write_router;
 
 pub use self::apply::{
     create_apply_batch_system, Apply, ApplyBatchSystem, ApplyMetrics, ApplyRes, ApplyRouter,
@@ -26,3 +27,9 @@ pub use self::store::{
     create_raft_batch_system, new_compaction_listener, RaftBatchSystem, RaftPollerBuilder,
     RaftRouter, StoreInfo,
 };
+pub use self::write_router::{
+    collect_states, summarize_reschedule_activity, RandomSelector, RescheduleActivitySummary,
+    RescheduleGroup, RoundRobinSelector, RoutingState, ScriptedSelector, SchedulingEvent, WriteMsg,
+    WriteRouter, WriteRouterContext, WriteRouterError, WriteRouterState, WriteSenders,
+    WriteSendersDescriptor, WriterSelector, WriterStat,
+};
@@ -230,4 +230,79 @@ lazy_static! {
             "tikv_raftstore_read_index_pending",
             "pending read index count"
         ).unwrap();
+
+    pub static ref STORE_IO_WRITER_QUEUE_STARVED_COUNTER: IntCounter =
+        register_int_counter!(
+            "tikv_raftstore_io_writer_queue_starved_total",
+            "Total number of times a peer's oldest buffered write msg was found to have aged past the starvation threshold"
+        ).unwrap();
+
+    pub static ref STORE_IO_WRITE_BLOCK_WAIT_HISTOGRAM: HistogramVec =
+        register_histogram_vec!(
+            "tikv_raftstore_io_write_block_wait_seconds",
+            "Bucketed histogram of time spent blocked before Config::io_blocking_send_timeout gave up and buffered the msg instead, labeled by why the send blocked and the WriteMsg::kind that blocked",
+            &["cause", "kind"],
+            exponential_buckets(0.0005, 2.0, 20).unwrap()
+        ).unwrap();
+
+    pub static ref STORE_IO_SELECTION_LATENCY_HISTOGRAM: Histogram =
+        register_histogram!(
+            "tikv_raftstore_io_selection_latency_seconds",
+            "Bucketed histogram of WriteRouter::should_send's own execution time, sampled when Config::io_reschedule_measure_selection_latency is set",
+            exponential_buckets(0.0000001, 2.0, 20).unwrap()
+        ).unwrap();
+
+    pub static ref STORE_IO_RESCHEDULE_PENDING_TASKS_TOTAL_GAUGE: IntGauge =
+        register_int_gauge!(
+            "tikv_raftstore_io_reschedule_pending_tasks_total",
+            "Total weight of write msgs currently buffered across all peers' WriteRouters, awaiting a reschedule or blocked send to resolve. Weight per msg defaults to 1, see WriteRouterContext::pending_task_weight"
+        ).unwrap();
+
+    pub static ref STORE_IO_WRITER_ASSIGNMENT_DURATION_HISTOGRAM: Histogram =
+        register_histogram!(
+            "tikv_raftstore_io_writer_assignment_duration_seconds",
+            "Bucketed histogram of how long a peer stayed pinned to a single writer before its next reschedule completed",
+            exponential_buckets(1.0, 2.0, 20).unwrap()
+        ).unwrap();
+
+    pub static ref STORE_IO_RESCHEDULE_COMPLETION_GAP_HISTOGRAM: Histogram =
+        register_histogram!(
+            "tikv_raftstore_io_reschedule_completion_gap",
+            "Bucketed histogram of how far the persisted number observed at reschedule completion exceeded the write number that completion was waiting on",
+            exponential_buckets(1.0, 2.0, 20).unwrap()
+        ).unwrap();
+
+    pub static ref STORE_IO_RESCHEDULE_BAILOUT_TOTAL: IntCounterVec =
+        register_int_counter_vec!(
+            "tikv_raftstore_io_reschedule_bailout_total",
+            "Total number of in-flight reschedules abandoned without completing, labeled by why",
+            &["reason"]
+        ).unwrap();
+
+    pub static ref STORE_IO_WRITER_SELECTED_TOTAL: IntCounterVec =
+        register_int_counter_vec!(
+            "tikv_raftstore_io_writer_selected_total",
+            "Total number of write msgs actually dispatched to each writer, labeled by writer_id",
+            &["writer_id"]
+        ).unwrap();
+
+    pub static ref STORE_IO_RESCHEDULE_WAIT_DURATION_HISTOGRAM: Histogram =
+        register_histogram!(
+            "tikv_raftstore_io_reschedule_wait_duration_seconds",
+            "Bucketed histogram of how long a peer spent with a reschedule in flight, from the reschedule starting to its completion being observed",
+            exponential_buckets(1.0, 2.0, 20).unwrap()
+        ).unwrap();
+
+    pub static ref STORE_IO_SENDER_SIZE_LAG: IntGauge =
+        register_int_gauge!(
+            "tikv_raftstore_io_sender_size_lag",
+            "How many fewer writers WriteSenders currently has than Config::store_io_pool_size calls for"
+        ).unwrap();
+
+    pub static ref STORE_IO_WRITE_BLOCK_TOTAL: IntCounterVec =
+        register_int_counter_vec!(
+            "tikv_raftstore_io_write_block_total",
+            "Total number of sends that found a writer's channel full and fell back to blocking, labeled by writer_id and WriteMsg::kind",
+            &["writer_id", "kind"]
+        ).unwrap();
 }